@@ -1,8 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 #[macro_use]
 mod singleton;
 
+#[cfg(feature = "std")]
 #[macro_use]
 mod preemptive;
 
-pub use singleton::Singleton;
+pub use singleton::{RelaxStrategy, Singleton, Spin};
+#[cfg(feature = "std")]
+pub use singleton::Yield;
+#[cfg(feature = "std")]
 pub use preemptive::PreemptiveSingleton;
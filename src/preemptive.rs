@@ -1,4 +1,4 @@
-use super::singleton::Singleton;
+use super::singleton::{RelaxStrategy, Singleton, Spin};
 use std::thread::{self, ThreadId};
 
 #[doc(hidden)]
@@ -11,9 +11,9 @@ unsafe impl<T> Sync for PreemptiveInner<T> {}
 
 /// A pointer type for holding non-shared global state in multi-thread environment.
 /// Only the thread that sucessfully put data in it can access the data.
-pub struct PreemptiveSingleton<T: Send> {
+pub struct PreemptiveSingleton<T, R: RelaxStrategy = Spin> {
     #[doc(hidden)]
-    pub singleton: Singleton<PreemptiveInner<T>>,
+    pub singleton: Singleton<PreemptiveInner<T>, R>,
 }
 
 /// Create an uninitialized preemptive singleton.
@@ -29,7 +29,7 @@ macro_rules! make_preemptive_singleton {
     };
 }
 
-impl<T: Send> PreemptiveSingleton<T> {
+impl<T, R: RelaxStrategy> PreemptiveSingleton<T, R> {
     /// Create an uninitialized singleton.
     #[cfg(feature = "const_fn")]
     pub const fn new() -> Self {
@@ -95,7 +95,7 @@ impl<T: Send> PreemptiveSingleton<T> {
     }
 }
 
-impl<T: Send> Drop for PreemptiveSingleton<T> {
+impl<T, R: RelaxStrategy> Drop for PreemptiveSingleton<T, R> {
     fn drop(&mut self) {
         unsafe {
             self.finalize();
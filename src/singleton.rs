@@ -1,6 +1,99 @@
-use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
-use std::ptr::null_mut;
-use std::mem::drop;
+#[cfg(not(feature = "single_thread"))]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "single_thread")]
+use core::cell::Cell;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::marker::PhantomData;
+
+/// The cell holding the state machine value.
+///
+/// Atomic under the default, multi-threaded build; a plain [`Cell`] under the
+/// `single_thread` feature, where the CAS and spin loop collapse to cheap
+/// non-atomic loads and stores.
+#[cfg(not(feature = "single_thread"))]
+type StateCell = AtomicUsize;
+#[cfg(feature = "single_thread")]
+type StateCell = Cell<usize>;
+
+#[cfg(not(feature = "single_thread"))]
+#[inline]
+fn state_load(state: &StateCell) -> usize {
+    state.load(Ordering::SeqCst)
+}
+
+#[cfg(not(feature = "single_thread"))]
+#[inline]
+fn state_swap(state: &StateCell, val: usize) -> usize {
+    state.swap(val, Ordering::SeqCst)
+}
+
+#[cfg(not(feature = "single_thread"))]
+#[inline]
+fn state_cas(state: &StateCell, old: usize, new: usize) -> usize {
+    match state.compare_exchange(old, new, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(prev) => prev,
+        Err(prev) => prev,
+    }
+}
+
+#[cfg(feature = "single_thread")]
+#[inline]
+fn state_load(state: &StateCell) -> usize {
+    state.get()
+}
+
+#[cfg(feature = "single_thread")]
+#[inline]
+fn state_swap(state: &StateCell, val: usize) -> usize {
+    let prev = state.get();
+    state.set(val);
+    prev
+}
+
+#[cfg(feature = "single_thread")]
+#[inline]
+fn state_cas(state: &StateCell, old: usize, new: usize) -> usize {
+    let cur = state.get();
+    if cur == old {
+        state.set(new);
+    }
+    cur
+}
+
+/// Strategy used to relax the CPU each time the spin loop observes that another
+/// thread is still initializing the singleton.
+pub trait RelaxStrategy {
+    /// Perform the relaxing operation during a period of contention.
+    fn relax();
+}
+
+/// A [`RelaxStrategy`] that hints to the processor that it is spinning via
+/// [`core::hint::spin_loop`].
+pub struct Spin;
+
+impl RelaxStrategy for Spin {
+    #[inline(always)]
+    fn relax() {
+        core::hint::spin_loop();
+    }
+}
+
+/// A [`RelaxStrategy`] that yields the current thread to the OS scheduler via
+/// [`std::thread::yield_now`].
+///
+/// Only available with the `std` feature enabled.
+#[cfg(feature = "std")]
+pub struct Yield;
+
+#[cfg(feature = "std")]
+impl RelaxStrategy for Yield {
+    #[inline(always)]
+    fn relax() {
+        std::thread::yield_now();
+    }
+}
 
 #[repr(usize)]
 enum SingletonState {
@@ -8,37 +101,99 @@ enum SingletonState {
     Loading = 1,
     Ready = 2,
     Finalized = 3,
+    Panicked = 4,
+}
+
+/// Demotes the state from `Loading` back to `Panicked` if the initializer
+/// unwinds, so other threads re-attempt initialization instead of spinning
+/// forever. Disarmed once `f` returns normally.
+struct PanicGuard<'a> {
+    state: &'a StateCell,
+    armed: bool,
+}
+
+impl<'a> PanicGuard<'a> {
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl<'a> Drop for PanicGuard<'a> {
+    fn drop(&mut self) {
+        if self.armed {
+            state_cas(
+                self.state,
+                SingletonState::Loading as _,
+                SingletonState::Panicked as _,
+            );
+        }
+    }
 }
 
 /// A pointer type for holding shared global state in multi-thread environment.
-pub struct Singleton<T: Send + Sync> {
+///
+/// The `R` type parameter selects the [`RelaxStrategy`] used while spinning on
+/// another thread's initialization; it defaults to [`Spin`].
+pub struct Singleton<T, R: RelaxStrategy = Spin> {
+    #[doc(hidden)]
+    pub state: StateCell,
     #[doc(hidden)]
-    pub state: AtomicUsize,
+    pub data: UnsafeCell<MaybeUninit<T>>,
     #[doc(hidden)]
-    pub ptr: AtomicPtr<T>,
+    pub _relax: PhantomData<R>,
 }
 
+// The `state` machine guarantees at most one thread ever writes `data`, and no
+// reader observes it before the `Ready` transition is published with `SeqCst`.
+#[cfg(not(feature = "single_thread"))]
+unsafe impl<T: Send + Sync, R: RelaxStrategy> Sync for Singleton<T, R> {}
+#[cfg(not(feature = "single_thread"))]
+unsafe impl<T: Send + Sync, R: RelaxStrategy> Send for Singleton<T, R> {}
+
+// Under `single_thread` there is no synchronization and no cross-thread sharing;
+// the unconditional `Sync`/`Send` impls let the same global `static`s compile,
+// trusting the program to be single-threaded (as rustc does in non-parallel
+// mode).
+#[cfg(feature = "single_thread")]
+unsafe impl<T, R: RelaxStrategy> Sync for Singleton<T, R> {}
+#[cfg(feature = "single_thread")]
+unsafe impl<T, R: RelaxStrategy> Send for Singleton<T, R> {}
+
 /// Create an uninitialized singleton.
 ///
 /// This is intended as a workaround before const fn stablizes.
 /// When const fn is stablized, you can just call Singleton::new().
+#[cfg(not(feature = "single_thread"))]
+#[macro_export]
+macro_rules! make_singleton {
+    () => {
+        Singleton {
+            state: ::core::sync::atomic::AtomicUsize::new(0),
+            data: ::core::cell::UnsafeCell::new(::core::mem::MaybeUninit::uninit()),
+            _relax: ::core::marker::PhantomData,
+        }
+    };
+}
+
+#[cfg(feature = "single_thread")]
 #[macro_export]
 macro_rules! make_singleton {
     () => {
         Singleton {
-            state: ::std::sync::atomic::AtomicUsize::new(0),
-            ptr: ::std::sync::atomic::AtomicPtr::new(::std::ptr::null_mut())
+            state: ::core::cell::Cell::new(0),
+            data: ::core::cell::UnsafeCell::new(::core::mem::MaybeUninit::uninit()),
+            _relax: ::core::marker::PhantomData,
         }
     };
 }
 
-impl<T: Send + Sync> Default for Singleton<T> {
+impl<T, R: RelaxStrategy> Default for Singleton<T, R> {
     fn default() -> Self {
         make_singleton!()
     }
 }
 
-impl<T: Send + Sync> Singleton<T> {
+impl<T, R: RelaxStrategy> Singleton<T, R> {
     /// Create an uninitialized singleton.
     #[cfg(feature = "const_fn")]
     pub const fn new() -> Self {
@@ -62,7 +217,51 @@ impl<T: Send + Sync> Singleton<T> {
 
     /// Access the singleton; or return `None` if it is not yet uninitialized.
     pub fn get_opt(&self) -> Option<&T> {
-        unsafe { self.ptr.load(Ordering::SeqCst).as_ref() }
+        if state_load(&self.state) == SingletonState::Ready as _ {
+            Some(unsafe { self.value_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Borrow the stored value. Caller must ensure the state is `Ready`.
+    unsafe fn value_ref(&self) -> &T {
+        &*(*self.data.get()).as_ptr()
+    }
+
+    /// Spin until the singleton becomes `Ready` and return a reference to it.
+    ///
+    /// Unlike `get_or_insert_with`, this never initializes the value itself; it
+    /// blocks waiting for whichever thread is responsible for producing it.
+    ///
+    /// Panics if the singleton has been finalized, or if the producing thread
+    /// panicked inside its initializer (`Panicked` state): since `wait` never
+    /// runs `f`, it cannot recover the value and would otherwise spin forever.
+    pub fn wait(&self) -> &T {
+        loop {
+            let cur_state = state_load(&self.state);
+            if cur_state == SingletonState::Ready as _ {
+                return unsafe { self.value_ref() };
+            } else if cur_state == SingletonState::Finalized as _ {
+                Self::error_finalized();
+                unreachable!();
+            } else if cur_state == SingletonState::Panicked as _ {
+                Self::error_panicked();
+                unreachable!();
+            }
+            // Initial / Loading: another thread owns initialization.
+            R::relax();
+        }
+    }
+
+    /// Return `Some` only if the singleton is already `Ready`, without spinning
+    /// or attempting initialization; `None` while it is `Initial` or `Loading`.
+    pub fn poll(&self) -> Option<&T> {
+        if state_load(&self.state) == SingletonState::Ready as _ {
+            Some(unsafe { self.value_ref() })
+        } else {
+            None
+        }
     }
 
     fn error_stateshift() {
@@ -75,36 +274,72 @@ impl<T: Send + Sync> Singleton<T> {
         panic!("singleton: trying to access a finalized singleton. Maybe caused by unsafe finalized() calling. ");
     }
 
+    fn error_panicked() {
+        // never type is not landing yet.
+        panic!("singleton: the thread responsible for initializing this singleton panicked. ");
+    }
+
     /// Access the singleton; initialize it with custom function if it is uninitialized.
+    ///
+    /// If `f` panics, the singleton is left in a `Panicked` state rather than
+    /// stuck in `Loading`; the next thread to observe `Panicked` claims the slot
+    /// and retries `f`, mirroring the poison-and-retry policy of `std::sync::Once`.
     pub fn get_or_insert_with<F>(&self, f: F) -> &T
     where
         F: FnOnce() -> T,
     {
-        if let Some(v) = unsafe { self.ptr.load(Ordering::SeqCst).as_ref() } {
+        if let Some(v) = self.get_opt() {
             return v;
         }
 
-        let mut cur_state = self.state.compare_and_swap(
+        let mut cur_state = state_cas(
+            &self.state,
             SingletonState::Initial as _,
             SingletonState::Loading as _,
-            Ordering::SeqCst,
         );
         'spin: loop {
             if cur_state == SingletonState::Loading as _ {
                 // some other threading is trying to initialize this singleton.
-                // wait and retry.
-                cur_state = self.state.load(Ordering::SeqCst);
+                // relax the CPU, wait and retry.
+                R::relax();
+                cur_state = state_load(&self.state);
+                continue 'spin;
+            } else if cur_state == SingletonState::Panicked as _ {
+                // the thread that owned initialization panicked inside `f`,
+                // leaving the singleton uninitialized. Try to claim the slot by
+                // moving `Panicked -> Loading`; on success route into the init
+                // path so `f` actually re-runs, otherwise fall back to spinning
+                // on whatever the winner left behind.
+                let prev = state_cas(
+                    &self.state,
+                    SingletonState::Panicked as _,
+                    SingletonState::Loading as _,
+                );
+                if prev == SingletonState::Panicked as _ {
+                    cur_state = SingletonState::Initial as _;
+                } else {
+                    cur_state = prev;
+                }
                 continue 'spin;
             } else if cur_state == SingletonState::Initial as _
                 || cur_state == SingletonState::Ready as _
             {
                 if cur_state == SingletonState::Initial as _ {
-                    let v = Box::into_raw(Box::new(f()));
-                    self.ptr.store(v, Ordering::SeqCst);
-                    cur_state = self.state.compare_and_swap(
+                    // arm a guard that demotes `Loading -> Panicked` if `f`
+                    // unwinds, so spinning threads retry instead of hanging.
+                    let guard = PanicGuard {
+                        state: &self.state,
+                        armed: true,
+                    };
+                    let value = f();
+                    guard.disarm();
+                    unsafe {
+                        (*self.data.get()).as_mut_ptr().write(value);
+                    }
+                    cur_state = state_cas(
+                        &self.state,
                         SingletonState::Loading as _,
                         SingletonState::Ready as _,
-                        Ordering::SeqCst,
                     );
 
                     if cur_state != SingletonState::Loading as _ {
@@ -113,12 +348,7 @@ impl<T: Send + Sync> Singleton<T> {
                     }
                 }
 
-                if let Some(v) = unsafe { self.ptr.load(Ordering::SeqCst).as_ref() } {
-                    return v;
-                } else {
-                    Self::error_stateshift();
-                    unreachable!();
-                }
+                return unsafe { self.value_ref() };
             }
 
             Self::error_finalized();
@@ -127,21 +357,80 @@ impl<T: Send + Sync> Singleton<T> {
         // unreachable!()
     }
 
+    /// Install an already-constructed `value` if the singleton is still in its
+    /// initial state, transitioning it to `Ready`.
+    ///
+    /// Returns `Err(value)`, handing the value back to the caller, if the
+    /// singleton is already being initialized, initialized or finalized, or if
+    /// an earlier initializer panicked (`Panicked` state); `set` does not
+    /// retry a panicked initialization the way `get_or_insert_with` does.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        let cur_state = state_cas(
+            &self.state,
+            SingletonState::Initial as _,
+            SingletonState::Loading as _,
+        );
+        if cur_state != SingletonState::Initial as _ {
+            return Err(value);
+        }
+        unsafe {
+            (*self.data.get()).as_mut_ptr().write(value);
+        }
+        let prev = state_cas(
+            &self.state,
+            SingletonState::Loading as _,
+            SingletonState::Ready as _,
+        );
+        if prev != SingletonState::Loading as _ {
+            Self::error_stateshift();
+            unreachable!();
+        }
+        Ok(())
+    }
+
+    /// Install an already-constructed `value` and return a reference to the
+    /// stored value.
+    ///
+    /// On success returns `Ok` with a reference to the just-installed value. On
+    /// contention returns `Err` with a reference to the value that ended up
+    /// stored and the rejected `value`.
+    ///
+    /// If another thread is still mid-initialization (`Loading`), this waits for
+    /// it to finish so it can hand back the stored reference. It panics, rather
+    /// than returning, if the singleton is finalized or if the producing thread
+    /// panicked, for the same reasons [`wait`](Self::wait) does.
+    pub fn try_insert(&self, value: T) -> Result<&T, (&T, T)> {
+        match self.set(value) {
+            Ok(()) => Ok(unsafe { self.value_ref() }),
+            Err(value) => loop {
+                let cur_state = state_load(&self.state);
+                if cur_state == SingletonState::Ready as _ {
+                    return Err((unsafe { self.value_ref() }, value));
+                } else if cur_state == SingletonState::Finalized as _ {
+                    Self::error_finalized();
+                    unreachable!();
+                } else if cur_state == SingletonState::Panicked as _ {
+                    Self::error_panicked();
+                    unreachable!();
+                }
+                // Loading: another thread is producing the value; wait for it.
+                R::relax();
+            },
+        }
+    }
+
     /// Put the singleton into a finalized state, destruct the singleton value if it is initialized.
     ///
     /// This is unsafe and only useful when the value holds other resources.
     pub unsafe fn finalize(&self) {
-        self.state
-            .store(SingletonState::Finalized as _, Ordering::SeqCst);
-        let old_ptr = self.ptr.swap(null_mut(), Ordering::SeqCst);
-        if old_ptr.is_null() {
-            return;
+        let prev = state_swap(&self.state, SingletonState::Finalized as _);
+        if prev == SingletonState::Ready as _ {
+            ptr::drop_in_place((*self.data.get()).as_mut_ptr());
         }
-        drop(Box::from_raw(old_ptr));
     }
 }
 
-impl<T: Send + Sync> Drop for Singleton<T> {
+impl<T, R: RelaxStrategy> Drop for Singleton<T, R> {
     fn drop(&mut self) {
         unsafe {
             self.finalize();
@@ -181,4 +470,91 @@ mod tests {
         let _b = SINGLETON_B.get();
         assert!(!SINGLETON_B.get_opt().is_none());
     }
+
+    struct C(usize);
+
+    static SINGLETON_C: Singleton<C> = make_singleton!();
+
+    #[test]
+    fn panicking_initializer_can_retry() {
+        use std::cell::Cell;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let calls = Cell::new(0usize);
+        // The first initialization panics, leaving the singleton `Panicked`
+        // rather than stuck in `Loading`.
+        let first = catch_unwind(AssertUnwindSafe(|| {
+            SINGLETON_C.get_or_insert_with(|| {
+                calls.set(calls.get() + 1);
+                panic!("boom");
+            });
+        }));
+        assert!(first.is_err());
+        assert!(SINGLETON_C.get_opt().is_none());
+
+        // A subsequent call must re-run `f` and succeed rather than hanging.
+        let c = SINGLETON_C.get_or_insert_with(|| {
+            calls.set(calls.get() + 1);
+            C(7)
+        });
+        assert_eq!(c.0, 7);
+        assert_eq!(calls.get(), 2);
+    }
+
+    struct D(usize);
+    impl Default for D {
+        fn default() -> Self {
+            D(9)
+        }
+    }
+
+    static SINGLETON_D: Singleton<D> = make_singleton!();
+
+    #[test]
+    fn poll_and_wait() {
+        // `poll` never initializes and returns `None` until `Ready`.
+        assert!(SINGLETON_D.poll().is_none());
+        let d = SINGLETON_D.get();
+        assert_eq!(d.0, 9);
+        // Once ready, both `poll` and `wait` hand back the stored reference.
+        let p = SINGLETON_D.poll().expect("ready after get");
+        let w = SINGLETON_D.wait();
+        assert_eq!(p as *const _, w as *const _);
+    }
+
+    struct E(usize);
+
+    static SINGLETON_E: Singleton<E> = make_singleton!();
+
+    #[test]
+    fn set_and_try_insert() {
+        // First `set` installs the value.
+        assert!(SINGLETON_E.set(E(1)).is_ok());
+        assert_eq!(SINGLETON_E.get_opt().unwrap().0, 1);
+
+        // A second `set` is rejected and hands the value back.
+        let rejected = SINGLETON_E.set(E(2));
+        assert!(matches!(rejected, Err(E(2))));
+
+        // `try_insert` on an already-initialized singleton returns the stored
+        // reference alongside the rejected value.
+        match SINGLETON_E.try_insert(E(3)) {
+            Ok(_) => panic!("expected rejection"),
+            Err((stored, rejected)) => {
+                assert_eq!(stored.0, 1);
+                assert_eq!(rejected.0, 3);
+            }
+        }
+    }
+
+    struct F(usize);
+
+    static SINGLETON_F: Singleton<F> = make_singleton!();
+
+    #[test]
+    fn try_insert_installs_when_empty() {
+        let r = SINGLETON_F.try_insert(F(5));
+        assert!(matches!(r, Ok(F(5))));
+        assert_eq!(SINGLETON_F.get_opt().unwrap().0, 5);
+    }
 }